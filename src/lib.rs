@@ -0,0 +1,19 @@
+extern crate bincode;
+extern crate bs58;
+extern crate chrono;
+extern crate clap;
+extern crate reqwest;
+#[macro_use]
+extern crate serde_json;
+
+pub mod budget_transaction;
+pub mod crdt;
+pub mod drone;
+pub mod fullnode;
+pub mod loader_program;
+pub mod loader_transaction;
+pub mod signature;
+pub mod system_transaction;
+pub mod thin_client;
+pub mod transaction;
+pub mod wallet;
@@ -0,0 +1,888 @@
+//! The `wallet` module provides command parsing and execution for the Solana wallet,
+//! kept separate from `src/bin/wallet.rs` so it can be exercised in tests without
+//! spawning a networked client.
+
+use bincode::serialize;
+use bs58;
+use chrono::{DateTime, Utc};
+use clap::ArgMatches;
+use crdt::NodeInfo;
+use drone::DroneRequest;
+use budget_transaction::BudgetTransaction;
+use loader_transaction::LoaderTransaction;
+use serde_json::Value;
+use signature::{KeyPair, KeyPairUtil, PublicKey, Signature};
+use system_transaction::SystemTransaction;
+use thin_client::ThinClient;
+use transaction::Transaction;
+use loader_program;
+use std::error;
+use std::fmt;
+use std::fs::{self, File};
+use std::io::prelude::*;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr, TcpStream};
+use std::path::Path;
+use std::thread::sleep;
+use std::time::Duration;
+
+// Size of each write transaction sent while uploading a program's userdata to the cluster.
+const DEPLOY_CHUNK_SIZE: usize = 256;
+
+/// Port the fullnode serves its JSON-RPC API on.
+pub const RPC_PORT: u16 = 8899;
+
+pub enum WalletCommand {
+    Address,
+    Balance(Option<PublicKey>),
+    AirDrop(i64),
+    Pay(
+        i64,
+        PublicKey,
+        Option<DateTime<Utc>>,
+        Option<PublicKey>,
+        Option<Vec<PublicKey>>,
+        bool,
+    ),
+    Witness(PublicKey),
+    TimeElapsed(PublicKey, PublicKey, DateTime<Utc>),
+    Cancel(PublicKey),
+    Confirm(Signature),
+    Deploy(String),
+    GetTransactionCount,
+    GenerateKeypair(String),
+}
+
+#[derive(Debug, Clone)]
+pub enum WalletError {
+    CommandNotRecognized(String),
+    BadParameter(String),
+    RpcRequestError(String),
+    DeployError(String),
+}
+
+impl fmt::Display for WalletError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "invalid")
+    }
+}
+
+impl error::Error for WalletError {
+    fn description(&self) -> &str {
+        "invalid"
+    }
+
+    fn cause(&self) -> Option<&error::Error> {
+        // Generic error, underlying cause isn't tracked.
+        None
+    }
+}
+
+pub struct WalletConfig {
+    pub leader: NodeInfo,
+    pub id: KeyPair,
+    pub drone_addr: SocketAddr,
+    pub rpc_addr: SocketAddr,
+    pub timeout: Duration,
+    pub command: WalletCommand,
+}
+
+impl Default for WalletConfig {
+    fn default() -> WalletConfig {
+        let default_addr = SocketAddr::new(IpAddr::V4(Ipv4Addr::new(0, 0, 0, 0)), 8000);
+        let mut rpc_addr = default_addr;
+        rpc_addr.set_port(RPC_PORT);
+        WalletConfig {
+            leader: NodeInfo::new_leader(&default_addr),
+            id: KeyPair::new(),
+            drone_addr: default_addr,
+            rpc_addr,
+            timeout: Duration::new(10, 0),
+            command: WalletCommand::Balance(None),
+        }
+    }
+}
+
+fn parse_pubkey(name: &str) -> Result<PublicKey, WalletError> {
+    let pubkey_vec = bs58::decode(name)
+        .into_vec()
+        .map_err(|_| WalletError::BadParameter("Invalid public key".to_string()))?;
+
+    if pubkey_vec.len() != std::mem::size_of::<PublicKey>() {
+        display_actions();
+        return Err(WalletError::BadParameter("Invalid public key".to_string()));
+    }
+    Ok(PublicKey::clone_from_slice(&pubkey_vec))
+}
+
+/// Turn the parsed clap subcommand into a `WalletCommand`. This is a pure function of
+/// its arguments so it can be unit tested without a running cluster.
+pub fn parse_command(
+    pubkey: PublicKey,
+    matches: &ArgMatches,
+) -> Result<WalletCommand, Box<error::Error>> {
+    let command = match matches.subcommand() {
+        ("airdrop", Some(airdrop_matches)) => {
+            let tokens = airdrop_matches.value_of("tokens").unwrap().parse()?;
+            Ok(WalletCommand::AirDrop(tokens))
+        }
+        ("pay", Some(pay_matches)) => {
+            let to = if pay_matches.is_present("to") {
+                parse_pubkey(pay_matches.value_of("to").unwrap())?
+            } else {
+                pubkey
+            };
+
+            let tokens = pay_matches.value_of("tokens").unwrap().parse()?;
+
+            let timestamp = if pay_matches.is_present("after") {
+                Some(
+                    pay_matches
+                        .value_of("after")
+                        .unwrap()
+                        .parse::<DateTime<Utc>>()?,
+                )
+            } else {
+                None
+            };
+
+            let timestamp_pubkey = if pay_matches.is_present("require-timestamp-from") {
+                Some(parse_pubkey(
+                    pay_matches.value_of("require-timestamp-from").unwrap(),
+                )?)
+            } else {
+                None
+            };
+
+            let witnesses = if pay_matches.is_present("require-signature-from") {
+                let mut witnesses = vec![];
+                for pubkey_string in pay_matches.values_of("require-signature-from").unwrap() {
+                    witnesses.push(parse_pubkey(pubkey_string)?);
+                }
+                Some(witnesses)
+            } else {
+                None
+            };
+
+            let cancelable = pay_matches.is_present("cancelable");
+
+            if timestamp.is_some() && witnesses.is_some() {
+                return Err(WalletError::BadParameter(
+                    "`--after` and `--require-signature-from` cannot be combined; a contract \
+                     can release on a timestamp or on witness signatures, not both"
+                        .to_string(),
+                ).into());
+            }
+
+            // Don't rely on clap's `.requires("after")` alone to catch this; a caller
+            // that builds ArgMatches directly (as the test module does) can still
+            // produce a `require-timestamp-from` with no `after`, which would
+            // otherwise be silently dropped once process_command routes on it.
+            if timestamp_pubkey.is_some() && timestamp.is_none() {
+                return Err(WalletError::BadParameter(
+                    "`--require-timestamp-from` requires `--after`; a timestamp-release \
+                     contract needs a release time"
+                        .to_string(),
+                ).into());
+            }
+
+            // A cancelable contract still needs a release condition to cancel before;
+            // `--cancelable` on its own would create a when-signed contract with no
+            // witnesses, which either releases immediately or is permanently stuck.
+            if cancelable && timestamp.is_none() && witnesses.is_none() {
+                return Err(WalletError::BadParameter(
+                    "`--cancelable` must be combined with `--after` or \
+                     `--require-signature-from`"
+                        .to_string(),
+                ).into());
+            }
+
+            Ok(WalletCommand::Pay(
+                tokens,
+                to,
+                timestamp,
+                timestamp_pubkey,
+                witnesses,
+                cancelable,
+            ))
+        }
+        ("witness", Some(witness_matches)) => {
+            let contract = parse_pubkey(witness_matches.value_of("contract").unwrap())?;
+            Ok(WalletCommand::Witness(contract))
+        }
+        ("time-elapsed", Some(time_elapsed_matches)) => {
+            let contract = parse_pubkey(time_elapsed_matches.value_of("contract").unwrap())?;
+            let to = parse_pubkey(time_elapsed_matches.value_of("to").unwrap())?;
+            let timestamp = time_elapsed_matches
+                .value_of("timestamp")
+                .unwrap()
+                .parse::<DateTime<Utc>>()?;
+            Ok(WalletCommand::TimeElapsed(contract, to, timestamp))
+        }
+        ("cancel", Some(cancel_matches)) => {
+            let contract = parse_pubkey(cancel_matches.value_of("contract").unwrap())?;
+            Ok(WalletCommand::Cancel(contract))
+        }
+        ("confirm", Some(confirm_matches)) => {
+            let sig_vec = bs58::decode(confirm_matches.value_of("signature").unwrap())
+                .into_vec()
+                .expect("base58-encoded signature");
+
+            if sig_vec.len() == std::mem::size_of::<Signature>() {
+                let sig = Signature::clone_from_slice(&sig_vec);
+                Ok(WalletCommand::Confirm(sig))
+            } else {
+                display_actions();
+                Err(WalletError::BadParameter("Invalid signature".to_string()))
+            }
+        }
+        ("balance", Some(balance_matches)) => {
+            let pubkey = if balance_matches.is_present("pubkey") {
+                Some(parse_pubkey(balance_matches.value_of("pubkey").unwrap())?)
+            } else {
+                None
+            };
+            Ok(WalletCommand::Balance(pubkey))
+        }
+        ("address", Some(_address_matches)) => Ok(WalletCommand::Address),
+        ("deploy", Some(deploy_matches)) => Ok(WalletCommand::Deploy(
+            deploy_matches
+                .value_of("program_location")
+                .unwrap()
+                .to_string(),
+        )),
+        ("get-transaction-count", Some(_get_transaction_count_matches)) => {
+            Ok(WalletCommand::GetTransactionCount)
+        }
+        ("keygen", Some(_keygen_matches)) => Ok(WalletCommand::GenerateKeypair(
+            matches.value_of("keypair").unwrap().to_string(),
+        )),
+        ("", None) => {
+            display_actions();
+            Err(WalletError::CommandNotRecognized(
+                "no subcommand given".to_string(),
+            ))
+        }
+        _ => unreachable!(),
+    }?;
+
+    Ok(command)
+}
+
+pub fn process_command(
+    config: &WalletConfig,
+    client: &mut ThinClient,
+) -> Result<(), Box<error::Error>> {
+    match config.command {
+        // Print the wallet's public key
+        WalletCommand::Address => {
+            println!("{}", bs58::encode(config.id.pubkey()).into_string());
+        }
+        WalletCommand::Balance(pubkey) => {
+            let pubkey = pubkey.unwrap_or_else(|| config.id.pubkey());
+            println!("Balance requested...");
+            let params = json!([bs58::encode(pubkey).into_string()]);
+            let balance = process_rpc_request(&config.rpc_addr, "getBalance", Some(params));
+            match balance {
+                Ok(balance) => {
+                    println!("Your balance is: {}", balance);
+                }
+                Err(error) => {
+                    println!("An error occurred: {}", error);
+                }
+            }
+        }
+        // Request an airdrop from Solana Drone;
+        // the requested amount is the command's `tokens` argument
+        WalletCommand::AirDrop(tokens) => {
+            println!("Airdrop requested...");
+            println!("Airdropping {:?} tokens", tokens);
+            request_airdrop(&config.drone_addr, &config.id, tokens as u64, config.timeout)?;
+            // TODO: return airdrop Result from Drone
+            sleep(Duration::from_millis(100));
+            let params = json!([bs58::encode(config.id.pubkey()).into_string()]);
+            let balance = process_rpc_request(&config.rpc_addr, "getBalance", Some(params));
+            match balance {
+                Ok(balance) => {
+                    println!("Your balance is: {}", balance);
+                }
+                Err(error) => {
+                    println!("An error occurred: {}", error);
+                }
+            }
+        }
+        // Send `tokens` to `to` in one transaction; with a time lock, witnesses or
+        // `cancelable`, the tokens go to a new contract account instead
+        WalletCommand::Pay(tokens, to, timestamp, timestamp_pubkey, ref witnesses, cancelable) => {
+            let last_id = client.get_last_id();
+
+            if timestamp.is_some() || witnesses.is_some() || cancelable {
+                let contract = KeyPair::new();
+                let cancelable_pubkey = if cancelable {
+                    Some(config.id.pubkey())
+                } else {
+                    None
+                };
+
+                let tx = if let Some(dt) = timestamp {
+                    let dt_pubkey = timestamp_pubkey.unwrap_or_else(|| config.id.pubkey());
+                    Transaction::budget_new_on_date(
+                        &config.id,
+                        to,
+                        contract.pubkey(),
+                        dt,
+                        dt_pubkey,
+                        cancelable_pubkey,
+                        tokens,
+                        last_id,
+                    )
+                } else {
+                    Transaction::budget_new_when_signed(
+                        &config.id,
+                        to,
+                        contract.pubkey(),
+                        witnesses.clone().unwrap_or_default(),
+                        cancelable_pubkey,
+                        tokens,
+                        last_id,
+                    )
+                };
+                let sig = client.transfer_signed(&tx)?;
+
+                println!("{}", bs58::encode(sig).into_string());
+                println!("Contract: {}", bs58::encode(contract.pubkey()).into_string());
+            } else {
+                let sig = client.transfer(tokens, &config.id, to, &last_id)?;
+                println!("{}", bs58::encode(sig).into_string());
+                return Ok(());
+            }
+        }
+        WalletCommand::Witness(contract) => {
+            let last_id = client.get_last_id();
+            let tx = Transaction::budget_new_signature(&config.id, contract, last_id);
+            let sig = client.transfer_signed(&tx)?;
+            println!("{}", bs58::encode(sig).into_string());
+        }
+        WalletCommand::TimeElapsed(contract, to, dt) => {
+            let last_id = client.get_last_id();
+            let tx = Transaction::budget_new_timestamp(&config.id, contract, to, dt, last_id);
+            let sig = client.transfer_signed(&tx)?;
+            println!("{}", bs58::encode(sig).into_string());
+        }
+        WalletCommand::Cancel(contract) => {
+            let last_id = client.get_last_id();
+            let tx = Transaction::budget_new_cancel(&config.id, contract, last_id);
+            let sig = client.transfer_signed(&tx)?;
+            println!("{}", bs58::encode(sig).into_string());
+        }
+        // Confirm the last client transaction by signature
+        WalletCommand::Confirm(sig) => {
+            let params = json!([bs58::encode(sig).into_string()]);
+            let status = process_rpc_request(&config.rpc_addr, "getSignatureStatus", Some(params));
+            match status {
+                Ok(Value::String(ref status)) if status == "Confirmed" => {
+                    println!("Confirmed");
+                }
+                Ok(_) => {
+                    println!("Not found");
+                }
+                Err(error) => {
+                    println!("An error occurred: {}", error);
+                }
+            }
+        }
+        WalletCommand::Deploy(ref program_location) => {
+            let mut program_userdata = Vec::new();
+            File::open(program_location.clone())?.read_to_end(&mut program_userdata)?;
+
+            let program_id = KeyPair::new();
+
+            let last_id = client.get_last_id();
+            let tx = Transaction::system_create(
+                &config.id,
+                program_id.pubkey(),
+                last_id,
+                1,
+                program_userdata.len() as u64,
+                loader_program::id(),
+            );
+            client.transfer_signed(&tx)?;
+
+            let mut offset = 0;
+            for chunk in program_userdata.chunks(DEPLOY_CHUNK_SIZE) {
+                send_deploy_chunk(client, &program_id, offset, chunk)?;
+                offset += chunk.len();
+            }
+
+            // A chunk can be dropped by the cluster; check what actually landed and
+            // resend anything missing before finalizing the program as executable.
+            retry_missing_chunks(client, &program_id, &program_userdata)?;
+
+            let last_id = client.get_last_id();
+            let tx = Transaction::loader_finalize(&program_id, loader_program::id(), last_id);
+            client.transfer_signed(&tx)?;
+
+            println!("{}", bs58::encode(program_id.pubkey()).into_string());
+        }
+        WalletCommand::GetTransactionCount => {
+            let transaction_count = process_rpc_request(&config.rpc_addr, "getTransactionCount", None);
+            match transaction_count {
+                Ok(count) => {
+                    println!("Transaction count: {}", count);
+                }
+                Err(error) => {
+                    println!("An error occurred: {}", error);
+                }
+            }
+        }
+        WalletCommand::GenerateKeypair(ref outfile) => {
+            let outfile = gen_keypair_file(outfile)?;
+            println!("Wrote new keypair to {}", outfile);
+        }
+    }
+    Ok(())
+}
+
+/// Expand a leading `~` (or `~/...`) to `$HOME`, so a default like
+/// `~/.config/solana/id.json` resolves to the user's home directory instead of
+/// being created literally under the current working directory.
+fn expand_tilde(path: &str) -> String {
+    if path == "~" {
+        return std::env::var("HOME").unwrap_or_else(|_| path.to_string());
+    }
+    if let Some(rest) = path.strip_prefix("~/") {
+        if let Ok(home) = std::env::var("HOME") {
+            return format!("{}/{}", home, rest);
+        }
+    }
+    path.to_string()
+}
+
+/// Generate a new Ed25519 keypair and write it as JSON to `outfile` (after expanding
+/// a leading `~`), creating any missing parent directories first. Refuses to clobber
+/// an existing file, since `outfile` is typically the keypair that controls a
+/// wallet's funds. The existence check and the file creation happen in a single
+/// `create_new` open so a concurrent writer can't sneak a file in between a
+/// separate check and create. Returns the expanded path that was written.
+pub fn gen_keypair_file(outfile: &str) -> Result<String, Box<error::Error>> {
+    let outfile = expand_tilde(outfile);
+    let keypair = KeyPair::new();
+    let serialized = serde_json::to_string(&keypair.to_bytes().to_vec())?;
+
+    if let Some(outdir) = Path::new(&outfile).parent() {
+        fs::create_dir_all(outdir)?;
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&outfile)
+        .map_err(|err| {
+            if err.kind() == std::io::ErrorKind::AlreadyExists {
+                WalletError::BadParameter(format!(
+                    "refusing to overwrite existing keypair file: {}",
+                    outfile
+                ))
+            } else {
+                WalletError::BadParameter(format!("unable to open {}: {}", outfile, err))
+            }
+        })?;
+    file.write_all(serialized.as_bytes())?;
+    Ok(outfile)
+}
+
+fn send_deploy_chunk(
+    client: &mut ThinClient,
+    program_id: &KeyPair,
+    offset: usize,
+    chunk: &[u8],
+) -> Result<(), Box<error::Error>> {
+    let last_id = client.get_last_id();
+    let tx = Transaction::loader_write(
+        program_id,
+        loader_program::id(),
+        offset as u32,
+        chunk.to_vec(),
+        last_id,
+    );
+    client.transfer_signed(&tx)?;
+    Ok(())
+}
+
+// Maximum number of poll/resend passes to make over a deploy's chunks before giving up.
+const MAX_CHUNK_RETRIES: u32 = 5;
+
+fn retry_missing_chunks(
+    client: &mut ThinClient,
+    program_id: &KeyPair,
+    program_userdata: &[u8],
+) -> Result<(), Box<error::Error>> {
+    for _ in 0..MAX_CHUNK_RETRIES {
+        let account_userdata = client.poll_get_account_userdata(&program_id.pubkey())?;
+
+        let mut offset = 0;
+        let mut all_landed = true;
+        for chunk in program_userdata.chunks(DEPLOY_CHUNK_SIZE) {
+            if account_userdata.get(offset..offset + chunk.len()) != Some(chunk) {
+                all_landed = false;
+                send_deploy_chunk(client, program_id, offset, chunk)?;
+            }
+            offset += chunk.len();
+        }
+
+        if all_landed {
+            return Ok(());
+        }
+    }
+
+    Err(WalletError::DeployError(format!(
+        "failed to land all program chunks after {} attempts",
+        MAX_CHUNK_RETRIES
+    )).into())
+}
+
+fn process_rpc_request(
+    rpc_addr: &SocketAddr,
+    method: &str,
+    params: Option<Value>,
+) -> Result<Value, WalletError> {
+    let request = json!({
+        "jsonrpc": "2.0",
+        "id": 1,
+        "method": method,
+        "params": params.unwrap_or(Value::Null),
+    });
+
+    let client = reqwest::Client::new();
+    let mut response = client
+        .post(&format!("http://{}", rpc_addr))
+        .json(&request)
+        .send()
+        .map_err(|err| WalletError::RpcRequestError(format!("{}", err)))?;
+
+    let response_json: Value = response
+        .json()
+        .map_err(|err| WalletError::RpcRequestError(format!("{}", err)))?;
+
+    if let Some(error) = response_json.get("error") {
+        return Err(WalletError::RpcRequestError(format!("{}", error)));
+    }
+
+    Ok(response_json["result"].clone())
+}
+
+fn display_actions() {
+    println!();
+    println!("Commands:");
+    println!("  address                Get your public key");
+    println!("  balance                Get the balance of your account, or another account");
+    println!("  airdrop                Request a batch of tokens");
+    println!("  pay                    Send tokens to a public key, optionally conditioned on time or signatures");
+    println!("  witness                Apply your signature to a contract awaiting witnesses");
+    println!("  time-elapsed           Apply a timestamp to a contract awaiting a time lock");
+    println!("  cancel                 Cancel a cancelable contract and reclaim its funds");
+    println!("  confirm                Confirm your last payment by signature");
+    println!("  deploy                 Deploy a program to the cluster");
+    println!("  get-transaction-count  Get the current transaction count");
+    println!("  keygen                 Generate a new keypair and write it to the --keypair path");
+    println!();
+}
+
+fn request_airdrop(
+    drone_addr: &SocketAddr,
+    id: &KeyPair,
+    tokens: u64,
+    timeout: Duration,
+) -> Result<(), Box<error::Error>> {
+    let mut stream = TcpStream::connect(drone_addr)?;
+    stream.set_write_timeout(Some(timeout))?;
+    let req = DroneRequest::GetAirdrop {
+        airdrop_request_amount: tokens,
+        client_public_key: id.pubkey(),
+    };
+    let tx = serialize(&req).expect("serialize drone request");
+    stream.write_all(&tx)?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::{App, Arg, SubCommand};
+    use std::net::{TcpListener, UdpSocket};
+    use std::thread;
+
+    // A ThinClient bound to loopback sockets that are never sent to; good enough to
+    // drive process_command arms (like Address) that never touch the network.
+    fn fake_client(config: &WalletConfig) -> ThinClient {
+        let requests_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        let transactions_socket = UdpSocket::bind("127.0.0.1:0").unwrap();
+        ThinClient::new(
+            config.leader.contact_info.rpu,
+            requests_socket,
+            config.leader.contact_info.tpu,
+            transactions_socket,
+        )
+    }
+
+    // Mirrors the `pay` subcommand wired up in src/bin/wallet.rs, minus the
+    // `conflicts_with` on `after` and the `requires("after")` on
+    // `require-timestamp-from`, so these tests can confirm parse_command
+    // itself rejects those combinations rather than relying solely on clap.
+    fn pay_app() -> App<'static, 'static> {
+        App::new("test").subcommand(
+            SubCommand::with_name("pay")
+                .arg(Arg::with_name("tokens").long("tokens").takes_value(true))
+                .arg(Arg::with_name("to").long("to").takes_value(true))
+                .arg(Arg::with_name("after").long("after").takes_value(true))
+                .arg(
+                    Arg::with_name("require-timestamp-from")
+                        .long("require-timestamp-from")
+                        .takes_value(true),
+                )
+                .arg(
+                    Arg::with_name("require-signature-from")
+                        .long("require-signature-from")
+                        .takes_value(true)
+                        .multiple(true),
+                )
+                .arg(Arg::with_name("cancelable").long("cancelable").takes_value(false)),
+        )
+    }
+
+    fn parse_pay(args: &[&str]) -> Result<WalletCommand, Box<error::Error>> {
+        let matches = pay_app().get_matches_from(args);
+        parse_command(KeyPair::new().pubkey(), &matches)
+    }
+
+    #[test]
+    fn test_parse_pay_simple() {
+        match parse_pay(&["test", "pay", "--tokens", "50"]).unwrap() {
+            WalletCommand::Pay(tokens, _, timestamp, timestamp_pubkey, witnesses, cancelable) => {
+                assert_eq!(tokens, 50);
+                assert_eq!(timestamp, None);
+                assert_eq!(timestamp_pubkey, None);
+                assert_eq!(witnesses, None);
+                assert_eq!(cancelable, false);
+            }
+            _ => panic!("expected WalletCommand::Pay"),
+        }
+    }
+
+    #[test]
+    fn test_parse_pay_with_after() {
+        match parse_pay(&[
+            "test",
+            "pay",
+            "--tokens",
+            "50",
+            "--after",
+            "2018-09-19T17:30:59Z",
+        ]).unwrap()
+        {
+            WalletCommand::Pay(_, _, timestamp, _, witnesses, _) => {
+                assert!(timestamp.is_some());
+                assert_eq!(witnesses, None);
+            }
+            _ => panic!("expected WalletCommand::Pay"),
+        }
+    }
+
+    #[test]
+    fn test_parse_pay_with_require_signature_from() {
+        let witness = KeyPair::new().pubkey();
+        let witness_string = bs58::encode(witness).into_string();
+        match parse_pay(&[
+            "test",
+            "pay",
+            "--tokens",
+            "50",
+            "--require-signature-from",
+            &witness_string,
+        ]).unwrap()
+        {
+            WalletCommand::Pay(_, _, timestamp, _, witnesses, _) => {
+                assert_eq!(timestamp, None);
+                assert_eq!(witnesses, Some(vec![witness]));
+            }
+            _ => panic!("expected WalletCommand::Pay"),
+        }
+    }
+
+    #[test]
+    fn test_parse_pay_with_cancelable() {
+        match parse_pay(&[
+            "test",
+            "pay",
+            "--tokens",
+            "50",
+            "--after",
+            "2018-09-19T17:30:59Z",
+            "--cancelable",
+        ]).unwrap()
+        {
+            WalletCommand::Pay(_, _, _, _, _, cancelable) => assert!(cancelable),
+            _ => panic!("expected WalletCommand::Pay"),
+        }
+    }
+
+    #[test]
+    fn test_parse_pay_rejects_bare_cancelable() {
+        let result = parse_pay(&["test", "pay", "--tokens", "50", "--cancelable"]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_pay_rejects_require_timestamp_from_without_after() {
+        let timestamp_pubkey_string = bs58::encode(KeyPair::new().pubkey()).into_string();
+        let result = parse_pay(&[
+            "test",
+            "pay",
+            "--tokens",
+            "50",
+            "--require-timestamp-from",
+            &timestamp_pubkey_string,
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_parse_pay_rejects_after_and_require_signature_from() {
+        let witness_string = bs58::encode(KeyPair::new().pubkey()).into_string();
+        let result = parse_pay(&[
+            "test",
+            "pay",
+            "--tokens",
+            "50",
+            "--after",
+            "2018-09-19T17:30:59Z",
+            "--require-signature-from",
+            &witness_string,
+        ]);
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_process_command_address() {
+        let mut config = WalletConfig::default();
+        config.command = WalletCommand::Address;
+        let mut client = fake_client(&config);
+
+        assert!(process_command(&config, &mut client).is_ok());
+    }
+
+    // Binds a listener that answers the next connection with a single canned
+    // JSON-RPC response, so process_rpc_request can be driven without a live
+    // fullnode. process_command's rpc_addr is plain config, so this needs no
+    // seam beyond pointing it at a loopback port we control.
+    fn mock_rpc_server(result: Value) -> SocketAddr {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let addr = listener.local_addr().unwrap();
+        thread::spawn(move || {
+            if let Ok((mut stream, _)) = listener.accept() {
+                let mut buf = [0u8; 1024];
+                let _ = stream.read(&mut buf);
+                let body = json!({"jsonrpc": "2.0", "id": 1, "result": result}).to_string();
+                let response = format!(
+                    "HTTP/1.1 200 OK\r\nContent-Type: application/json\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+                let _ = stream.write_all(response.as_bytes());
+            }
+        });
+        addr
+    }
+
+    #[test]
+    fn test_process_rpc_request() {
+        let rpc_addr = mock_rpc_server(Value::from(42));
+        let result = process_rpc_request(&rpc_addr, "getTransactionCount", None).unwrap();
+        assert_eq!(result, Value::from(42));
+    }
+
+    #[test]
+    fn test_process_command_balance() {
+        let mut config = WalletConfig::default();
+        config.rpc_addr = mock_rpc_server(Value::from(50));
+        config.command = WalletCommand::Balance(None);
+        let mut client = fake_client(&config);
+
+        assert!(process_command(&config, &mut client).is_ok());
+    }
+
+    #[test]
+    fn test_gen_keypair_file_creates_parent_dirs_and_valid_json() {
+        let mut outdir = std::env::temp_dir();
+        outdir.push(format!("solana-wallet-test-{}-{}", "keygen", std::process::id()));
+        outdir.push("nested");
+        let mut outfile = outdir.clone();
+        outfile.push("id.json");
+        let outfile = outfile.to_str().unwrap().to_string();
+
+        assert!(gen_keypair_file(&outfile).is_ok());
+
+        let contents = fs::read_to_string(&outfile).unwrap();
+        let bytes: Vec<u8> = serde_json::from_str(&contents).unwrap();
+        assert!(!bytes.is_empty());
+
+        fs::remove_dir_all(outdir.parent().unwrap()).unwrap();
+    }
+
+    #[test]
+    fn test_gen_keypair_file_refuses_to_overwrite() {
+        let mut outfile = std::env::temp_dir();
+        outfile.push(format!(
+            "solana-wallet-test-overwrite-{}.json",
+            std::process::id()
+        ));
+        let outfile = outfile.to_str().unwrap().to_string();
+
+        assert!(gen_keypair_file(&outfile).is_ok());
+        assert!(gen_keypair_file(&outfile).is_err());
+
+        fs::remove_file(&outfile).unwrap();
+    }
+
+    #[test]
+    fn test_expand_tilde() {
+        std::env::set_var("HOME", "/home/solana-test-user");
+        assert_eq!(expand_tilde("~"), "/home/solana-test-user");
+        assert_eq!(
+            expand_tilde("~/.config/solana/id.json"),
+            "/home/solana-test-user/.config/solana/id.json"
+        );
+        assert_eq!(expand_tilde("/absolute/id.json"), "/absolute/id.json");
+    }
+
+    #[test]
+    fn test_process_command_confirm_confirmed() {
+        let mut config = WalletConfig::default();
+        config.rpc_addr = mock_rpc_server(Value::from("Confirmed"));
+        let sig_bytes = vec![0u8; std::mem::size_of::<Signature>()];
+        config.command = WalletCommand::Confirm(Signature::clone_from_slice(&sig_bytes));
+        let mut client = fake_client(&config);
+
+        assert!(process_command(&config, &mut client).is_ok());
+    }
+
+    #[test]
+    fn test_process_command_confirm_not_found() {
+        let mut config = WalletConfig::default();
+        config.rpc_addr = mock_rpc_server(Value::from("Not found"));
+        let sig_bytes = vec![0u8; std::mem::size_of::<Signature>()];
+        config.command = WalletCommand::Confirm(Signature::clone_from_slice(&sig_bytes));
+        let mut client = fake_client(&config);
+
+        assert!(process_command(&config, &mut client).is_ok());
+    }
+
+    #[test]
+    fn test_process_command_get_transaction_count() {
+        let mut config = WalletConfig::default();
+        config.rpc_addr = mock_rpc_server(Value::from(1234));
+        config.command = WalletCommand::GetTransactionCount;
+        let mut client = fake_client(&config);
+
+        assert!(process_command(&config, &mut client).is_ok());
+    }
+}